@@ -1,45 +1,131 @@
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
 use itertools::Itertools;
-use std::{collections::HashMap, env, path::PathBuf};
+use std::{
+    collections::HashMap,
+    env,
+    io::{Read as _, SeekFrom, Write as _},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 use tokio::{
-    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
-    net::{TcpListener, TcpStream},
+    io::{
+        AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWrite,
+        AsyncWriteExt, BufReader,
+    },
+    net::TcpListener,
+    time::timeout,
 };
+use tokio_rustls::TlsAcceptor;
+
+/// How long to wait for the next request line on an idle keep-alive connection.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How many leading bytes of a file to inspect when guessing its content type.
+const SNIFF_PREFIX_LEN: usize = 1024;
+
+/// Loads a PEM certificate chain and private key into a TLS server config for
+/// `--tls-cert`/`--tls-key`.
+fn load_tls_config(cert_path: &Path, key_path: &Path) -> anyhow::Result<rustls::ServerConfig> {
+    let mut cert_reader = std::io::BufReader::new(std::fs::File::open(cert_path)?);
+    let certs = rustls_pemfile::certs(&mut cert_reader).collect::<Result<Vec<_>, _>>()?;
+
+    let mut key_reader = std::io::BufReader::new(std::fs::File::open(key_path)?);
+    let key = rustls_pemfile::private_key(&mut key_reader)?
+        .ok_or_else(|| anyhow::anyhow!("no private key found in {key_path:?}"))?;
 
-#[derive(PartialEq)]
+    let config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+
+    Ok(config)
+}
+
+/// Guesses the `Content-Type` for a served file, preferring its extension and
+/// falling back to sniffing the first few bytes for NUL bytes / invalid UTF-8.
+fn detect_content_type(path: &str, body: &[u8]) -> &'static str {
+    if let Some(ext) = std::path::Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+    {
+        match ext.to_lowercase().as_str() {
+            "html" | "htm" => return "text/html",
+            "json" => return "application/json",
+            "css" => return "text/css",
+            "js" => return "application/javascript",
+            "png" => return "image/png",
+            "jpg" | "jpeg" => return "image/jpeg",
+            "gif" => return "image/gif",
+            "svg" => return "image/svg+xml",
+            "txt" => return "text/plain; charset=utf-8",
+            _ => {}
+        }
+    }
+
+    let prefix = &body[..body.len().min(SNIFF_PREFIX_LEN)];
+    if prefix.contains(&0) || std::str::from_utf8(prefix).is_err() {
+        "application/octet-stream"
+    } else {
+        "text/plain; charset=utf-8"
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
 enum Method {
+    #[default]
     Get,
     Post,
     Put,
+    Head,
 }
 
-impl std::convert::From<&str> for Method {
-    fn from(input: &str) -> Self {
+impl std::convert::TryFrom<&str> for Method {
+    type Error = anyhow::Error;
+
+    fn try_from(input: &str) -> anyhow::Result<Self> {
         match input.to_lowercase().as_str() {
-            "get" => Method::Get,
-            "post" => Method::Post,
-            "put" => Method::Put,
-            e => panic!("unknown method {e}"),
+            "get" => Ok(Method::Get),
+            "post" => Ok(Method::Post),
+            "put" => Ok(Method::Put),
+            "head" => Ok(Method::Head),
+            other => anyhow::bail!("unsupported method {other}"),
         }
     }
 }
 
+#[derive(Default)]
 struct Request {
     path: String,
     headers: std::collections::HashMap<String, String>,
     method: Method,
     body: Vec<u8>,
+    params: HashMap<String, String>,
+}
+
+/// What `Request::parse` found on the wire: either a request we understand,
+/// or a verb we don't support (in which case the caller answers with a clean
+/// `501` instead of panicking, then closes the connection, since the rest of
+/// that request is left unread).
+enum ParsedRequest {
+    Supported(Request),
+    UnsupportedMethod,
 }
 
 impl Request {
-    async fn parse(buff: &mut BufReader<&mut TcpStream>) -> anyhow::Result<Self> {
+    async fn parse(buff: &mut (impl AsyncBufRead + Unpin)) -> anyhow::Result<ParsedRequest> {
         let mut line = String::new();
-        buff.read_line(&mut line).await?;
+        let n = buff.read_line(&mut line).await?;
+        anyhow::ensure!(n > 0, "connection closed");
+
         let (method, path, _version) = line
             .split_whitespace()
             .collect_tuple()
-            .expect("invalid first HTTP line");
+            .ok_or_else(|| anyhow::anyhow!("invalid first HTTP line"))?;
 
-        let method: Method = method.into();
+        let method = match Method::try_from(method) {
+            Ok(method) => method,
+            Err(_) => return Ok(ParsedRequest::UnsupportedMethod),
+        };
         let mut headers = HashMap::new();
         let mut line = String::new();
 
@@ -51,7 +137,9 @@ impl Request {
             if line == "\r\n" {
                 break;
             }
-            let (hname, hvalue) = line.split_once(": ").expect("invalid header line");
+            let (hname, hvalue) = line
+                .split_once(": ")
+                .ok_or_else(|| anyhow::anyhow!("invalid header line"))?;
             let hvalue = hvalue.trim();
             headers.insert(hname.to_string(), hvalue.to_string());
             line.clear();
@@ -69,32 +157,144 @@ impl Request {
             anyhow::ensure!(n == payload_size, "invalid body size");
         }
 
-        Ok(Self {
+        Ok(ParsedRequest::Supported(Self {
             path: path.into(),
             headers,
             method,
             body,
+            params: HashMap::new(),
+        }))
+    }
+
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(String::as_str)
+    }
+
+    fn supported_encodings(&self) -> Vec<String> {
+        self.headers.get("Accept-Encoding").map_or(Vec::new(), |x| {
+            x.split(',').map(|e| e.trim().to_lowercase()).collect()
         })
     }
 
-    fn supported_encodings(&self) -> Vec<&str> {
+    fn accepts_gzip(&self) -> bool {
+        self.supported_encodings().iter().any(|e| e == "gzip")
+    }
+
+    fn wants_close(&self) -> bool {
         self.headers
-            .get("Accept-Encoding")
-            .map_or(Vec::new(), |x| x.split(',').collect())
+            .get("Connection")
+            .is_some_and(|v| v.eq_ignore_ascii_case("close"))
+    }
+}
+
+#[derive(Clone)]
+enum Segment {
+    Literal(String),
+    Param(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RouteId {
+    Root,
+    UserAgent,
+    Echo,
+    FilesGet,
+    FilesPost,
+}
+
+enum RouteMatch {
+    Found(RouteId, HashMap<String, String>),
+    MethodNotAllowed,
+    NotFound,
+}
+
+/// A declarative `(Method, pattern) -> RouteId` table. Patterns split on `/`;
+/// `{name}` segments capture into `Request::params`, everything else must
+/// match literally.
+#[derive(Clone, Default)]
+struct Router {
+    routes: Vec<(Method, Vec<Segment>, RouteId)>,
+}
+
+impl Router {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn route(mut self, method: Method, pattern: &str, id: RouteId) -> Self {
+        let segments = pattern
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(
+                |s| match s.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                    Some(name) => Segment::Param(name.to_string()),
+                    None => Segment::Literal(s.to_string()),
+                },
+            )
+            .collect();
+        self.routes.push((method, segments, id));
+        self
+    }
+
+    fn matches(&self, method: Method, path: &str) -> RouteMatch {
+        let path_segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let mut path_matched = false;
+
+        for (route_method, segments, id) in &self.routes {
+            if segments.len() != path_segments.len() {
+                continue;
+            }
+
+            let mut params = HashMap::new();
+            let segments_match =
+                segments
+                    .iter()
+                    .zip(&path_segments)
+                    .all(|(seg, actual)| match seg {
+                        Segment::Literal(lit) => lit == actual,
+                        Segment::Param(name) => {
+                            params.insert(name.clone(), actual.to_string());
+                            true
+                        }
+                    });
+            if !segments_match {
+                continue;
+            }
+
+            path_matched = true;
+            if *route_method == method {
+                return RouteMatch::Found(*id, params);
+            }
+        }
+
+        if path_matched {
+            RouteMatch::MethodNotAllowed
+        } else {
+            RouteMatch::NotFound
+        }
     }
 }
 
-struct Response<'a> {
-    body: String,
+/// A response body, either buffered in memory or an open file streamed
+/// straight to the client via chunked transfer-encoding.
+enum Body {
+    Memory(Vec<u8>),
+    File(tokio::fs::File),
+}
+
+struct Response<'a, W: AsyncWrite + Unpin> {
+    body: Body,
+    body_precompressed: bool,
     status: u16,
-    stream: &'a mut TcpStream,
+    stream: &'a mut W,
     headers: HashMap<String, String>,
     request: &'a Request,
 }
-impl<'a> Response<'a> {
-    fn new(stream: &'a mut TcpStream, request: &'a Request) -> Self {
+impl<'a, W: AsyncWrite + Unpin> Response<'a, W> {
+    fn new(stream: &'a mut W, request: &'a Request) -> Self {
         Self {
-            body: String::new(),
+            body: Body::Memory(Vec::new()),
+            body_precompressed: false,
             status: 200,
             stream,
             headers: HashMap::new(),
@@ -107,8 +307,24 @@ impl<'a> Response<'a> {
         self
     }
 
-    fn body(&mut self, body: &str) -> &mut Self {
-        self.body = body.into();
+    fn body(&mut self, body: impl Into<Vec<u8>>) -> &mut Self {
+        self.body = Body::Memory(body.into());
+        self
+    }
+
+    /// Streams an open file as the body via `Transfer-Encoding: chunked`
+    /// instead of buffering it whole.
+    fn file_body(&mut self, file: tokio::fs::File) -> &mut Self {
+        self.body = Body::File(file);
+        self
+    }
+
+    /// Like `file_body`, but the file's contents are already in their
+    /// on-the-wire encoding (e.g. a `.gz` sibling), so `send` must not gzip
+    /// it again while streaming.
+    fn precompressed_file_body(&mut self, file: tokio::fs::File) -> &mut Self {
+        self.body = Body::File(file);
+        self.body_precompressed = true;
         self
     }
 
@@ -123,7 +339,9 @@ impl<'a> Response<'a> {
             201 => "201 Created",
             400 => "400 Bad Request",
             404 => "404 Not Found",
+            405 => "405 Method Not Allowed",
             500 => "500 Internal Server Error",
+            501 => "501 Not Implemented",
             _ => panic!("Unknown status code"),
         };
         self.stream
@@ -132,103 +350,337 @@ impl<'a> Response<'a> {
 
         self.append_own_headers();
 
-        for (hname, hvalue) in &self.headers {
-            self.stream
-                .write_all(format!("{hname}: {hvalue}\r\n").as_bytes())
-                .await?;
-        }
+        let gzip = !self.body_precompressed
+            && self.headers.get("Content-Encoding").map(String::as_str) == Some("gzip");
+        let head = self.request.method == Method::Head;
+        let body = std::mem::replace(&mut self.body, Body::Memory(Vec::new()));
 
-        if !self.body.is_empty() {
-            self.stream
-                .write_all(format!("Content-Length: {}\r\n\r\n", self.body.len()).as_bytes())
-                .await?;
-            self.stream.write_all(self.body.as_bytes()).await?;
-        } else {
-            self.stream.write_all(b"Content-Length: 0\r\n\r\n").await?;
+        match body {
+            Body::Memory(bytes) => {
+                let bytes = if gzip {
+                    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                    encoder.write_all(&bytes)?;
+                    encoder.finish()?
+                } else {
+                    bytes
+                };
+
+                for (hname, hvalue) in &self.headers {
+                    self.stream
+                        .write_all(format!("{hname}: {hvalue}\r\n").as_bytes())
+                        .await?;
+                }
+                self.stream
+                    .write_all(format!("Content-Length: {}\r\n\r\n", bytes.len()).as_bytes())
+                    .await?;
+                if !head {
+                    self.stream.write_all(&bytes).await?;
+                }
+            }
+            Body::File(mut file) if head => {
+                // HEAD reports the body size as a Content-Length instead of
+                // chunked framing, so read (and, if needed, compress in one
+                // pass) the file once to measure it. With gzip this is the
+                // size of a one-pass compression, which can differ from the
+                // total of the per-chunk flushed stream that a GET sends.
+                let mut bytes = Vec::new();
+                file.read_to_end(&mut bytes).await?;
+                let len = if gzip {
+                    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                    encoder.write_all(&bytes)?;
+                    encoder.finish()?.len()
+                } else {
+                    bytes.len()
+                };
+
+                for (hname, hvalue) in &self.headers {
+                    self.stream
+                        .write_all(format!("{hname}: {hvalue}\r\n").as_bytes())
+                        .await?;
+                }
+                self.stream
+                    .write_all(format!("Content-Length: {len}\r\n\r\n").as_bytes())
+                    .await?;
+            }
+            Body::File(mut file) => {
+                self.headers
+                    .entry("Transfer-Encoding".into())
+                    .or_insert("chunked".into());
+
+                for (hname, hvalue) in &self.headers {
+                    self.stream
+                        .write_all(format!("{hname}: {hvalue}\r\n").as_bytes())
+                        .await?;
+                }
+                self.stream.write_all(b"\r\n").await?;
+
+                stream_file_chunked(&mut file, self.stream, gzip).await?;
+            }
         }
         Ok(())
     }
 
     fn append_own_headers(&mut self) {
-        if self.request.supported_encodings().contains(&"gzip") {
+        if self.request.accepts_gzip() {
             self.headers
                 .entry("Content-Encoding".into())
                 .or_insert("gzip".into());
         }
+        let connection = if self.request.wants_close() {
+            "close"
+        } else {
+            "keep-alive"
+        };
+        self.headers
+            .entry("Connection".into())
+            .or_insert(connection.into());
+    }
+}
+
+const CHUNK_BUF_LEN: usize = 64 * 1024;
+
+/// Writes `data` as a single `Transfer-Encoding: chunked` frame. A no-op for
+/// an empty slice, since a zero-length chunk is the stream terminator.
+async fn write_chunk(stream: &mut (impl AsyncWrite + Unpin), data: &[u8]) -> anyhow::Result<()> {
+    if data.is_empty() {
+        return Ok(());
+    }
+    stream
+        .write_all(format!("{:x}\r\n", data.len()).as_bytes())
+        .await?;
+    stream.write_all(data).await?;
+    stream.write_all(b"\r\n").await?;
+    Ok(())
+}
+
+/// Streams `file` to `stream` as a series of chunked frames, optionally
+/// gzip-compressing each chunk on the fly so large files never need to be
+/// buffered whole in either their plain or compressed form.
+async fn stream_file_chunked(
+    file: &mut tokio::fs::File,
+    stream: &mut (impl AsyncWrite + Unpin),
+    gzip: bool,
+) -> anyhow::Result<()> {
+    let mut encoder = gzip.then(|| GzEncoder::new(Vec::new(), Compression::default()));
+    let mut buf = vec![0u8; CHUNK_BUF_LEN];
+
+    loop {
+        let read = file.read(&mut buf).await?;
+        if read == 0 {
+            break;
+        }
+        match &mut encoder {
+            Some(encoder) => {
+                encoder.write_all(&buf[..read])?;
+                encoder.flush()?;
+                let compressed = std::mem::take(encoder.get_mut());
+                write_chunk(stream, &compressed).await?;
+            }
+            None => write_chunk(stream, &buf[..read]).await?,
+        }
+    }
+
+    if let Some(encoder) = encoder {
+        let trailer = encoder.finish()?;
+        write_chunk(stream, &trailer).await?;
     }
+
+    stream.write_all(b"0\r\n\r\n").await?;
+    Ok(())
+}
+
+fn build_router() -> Router {
+    Router::new()
+        .route(Method::Get, "/", RouteId::Root)
+        .route(Method::Head, "/", RouteId::Root)
+        .route(Method::Get, "/user-agent", RouteId::UserAgent)
+        .route(Method::Head, "/user-agent", RouteId::UserAgent)
+        .route(Method::Get, "/echo/{text}", RouteId::Echo)
+        .route(Method::Head, "/echo/{text}", RouteId::Echo)
+        .route(Method::Get, "/files/{name}", RouteId::FilesGet)
+        .route(Method::Head, "/files/{name}", RouteId::FilesGet)
+        .route(Method::Post, "/files/{name}", RouteId::FilesPost)
 }
 
-async fn handle_connection(mut stream: TcpStream, config: Config) -> anyhow::Result<()> {
+async fn handle_connection(
+    stream: impl AsyncRead + AsyncWrite + Unpin,
+    config: Config,
+    router: Router,
+) -> anyhow::Result<()> {
     println!("accepted new connection");
 
-    let mut b = BufReader::new(&mut stream);
-    let req = Request::parse(&mut b).await?;
+    let (rx, mut tx) = tokio::io::split(stream);
+    let mut reader = BufReader::new(rx);
 
-    if req.path == "/" {
-        Response::new(&mut stream, &req).status(200).send().await?;
-    } else if req.path == "/user-agent" {
-        let ua = req.headers.get("User-Agent").cloned().unwrap_or_default();
+    loop {
+        let mut req = match timeout(IDLE_TIMEOUT, Request::parse(&mut reader)).await {
+            Ok(Ok(ParsedRequest::Supported(req))) => req,
+            Ok(Ok(ParsedRequest::UnsupportedMethod)) => {
+                Response::new(&mut tx, &Request::default())
+                    .status(501)
+                    .header("Connection", "close")
+                    .send()
+                    .await?;
+                break;
+            }
+            Ok(Err(_)) | Err(_) => break,
+        };
 
-        Response::new(&mut stream, &req)
-            .status(200)
-            .header("Content-Type", "text/plain")
-            .body(&ua)
-            .send()
-            .await?;
-    } else if req.path.starts_with("/echo/") {
-        let payload = req
-            .path
-            .strip_prefix("/echo/")
-            .expect("some payload should exist");
-
-        Response::new(&mut stream, &req)
-            .status(200)
-            .header("Content-Type", "text/plain")
-            .body(payload)
-            .send()
-            .await?;
-    } else if req.path.starts_with("/files/") {
-        if config.static_files.is_none() {
-            return Response::new(&mut stream, &req).status(404).send().await;
+        let close = req.wants_close();
+        match router.matches(req.method, &req.path) {
+            RouteMatch::Found(id, params) => {
+                req.params = params;
+                dispatch(id, &req, &mut tx, &config).await?;
+            }
+            RouteMatch::MethodNotAllowed => {
+                Response::new(&mut tx, &req).status(405).send().await?;
+            }
+            RouteMatch::NotFound => {
+                Response::new(&mut tx, &req).status(404).send().await?;
+            }
+        }
+        if close {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+async fn dispatch(
+    id: RouteId,
+    req: &Request,
+    stream: &mut (impl AsyncWrite + Unpin),
+    config: &Config,
+) -> anyhow::Result<()> {
+    match id {
+        RouteId::Root => {
+            Response::new(stream, req).status(200).send().await?;
+        }
+        RouteId::UserAgent => {
+            let ua = req.headers.get("User-Agent").cloned().unwrap_or_default();
+
+            Response::new(stream, req)
+                .status(200)
+                .header("Content-Type", "text/plain")
+                .body(ua.into_bytes())
+                .send()
+                .await?;
+        }
+        RouteId::Echo => {
+            let payload = req.param("text").expect("route guarantees {text}");
+
+            Response::new(stream, req)
+                .status(200)
+                .header("Content-Type", "text/plain")
+                .body(payload.as_bytes())
+                .send()
+                .await?;
         }
-        let entity = req
-            .path
-            .strip_prefix("/files/")
-            .expect("some entity path should exist");
-
-        let fpath = format!(
-            "{}/{entity}",
-            config.static_files.unwrap().to_str().unwrap()
-        );
-        if req.method == Method::Get {
-            if !tokio::fs::try_exists(&fpath).await? {
-                return Response::new(&mut stream, &req).status(404).send().await;
+        RouteId::FilesGet => {
+            if config.static_files.is_none() {
+                return Response::new(stream, req).status(404).send().await;
             }
-            match tokio::fs::read_to_string(&fpath).await {
-                Ok(body) => {
-                    return Response::new(&mut stream, &req)
+            let entity = req.param("name").expect("route guarantees {name}");
+            let fpath = format!(
+                "{}/{entity}",
+                config.static_files.as_ref().unwrap().to_str().unwrap()
+            );
+
+            let gz_path = format!("{fpath}.gz");
+            let has_plain = tokio::fs::try_exists(&fpath).await?;
+            let has_gz = tokio::fs::try_exists(&gz_path).await?;
+            if !has_plain && !has_gz {
+                return Response::new(stream, req).status(404).send().await;
+            }
+
+            if has_gz && req.accepts_gzip() {
+                return match tokio::fs::File::open(&gz_path).await {
+                    Ok(file) => {
+                        // Content is still gzip-compressed, so only the
+                        // extension can inform the content type here.
+                        let content_type = detect_content_type(&fpath, &[]);
+                        Response::new(stream, req)
+                            .status(200)
+                            .header("Content-Type", content_type)
+                            .header("Content-Encoding", "gzip")
+                            .precompressed_file_body(file)
+                            .send()
+                            .await
+                    }
+                    Err(err) => {
+                        Response::new(stream, req)
+                            .status(500)
+                            .body(format!("failed to read file: {err}").into_bytes())
+                            .send()
+                            .await
+                    }
+                };
+            }
+
+            if has_plain {
+                return match tokio::fs::File::open(&fpath).await {
+                    Ok(mut file) => {
+                        let mut prefix = vec![0u8; SNIFF_PREFIX_LEN];
+                        let read = file.read(&mut prefix).await?;
+                        prefix.truncate(read);
+                        file.seek(SeekFrom::Start(0)).await?;
+
+                        let content_type = detect_content_type(&fpath, &prefix);
+                        Response::new(stream, req)
+                            .status(200)
+                            .header("Content-Type", content_type)
+                            .file_body(file)
+                            .send()
+                            .await
+                    }
+                    Err(err) => {
+                        Response::new(stream, req)
+                            .status(500)
+                            .body(format!("failed to read file: {err}").into_bytes())
+                            .send()
+                            .await
+                    }
+                };
+            }
+
+            // Only a .gz sibling exists and the client doesn't want gzip:
+            // GzDecoder needs a synchronous Read, so decode it fully in
+            // memory rather than streaming.
+            match tokio::fs::read(&gz_path).await {
+                Ok(bytes) => {
+                    let mut decoder = GzDecoder::new(&bytes[..]);
+                    let mut decompressed = Vec::new();
+                    decoder.read_to_end(&mut decompressed)?;
+                    let content_type = detect_content_type(&fpath, &decompressed);
+                    Response::new(stream, req)
                         .status(200)
-                        .header("Content-Type", "application/octet-stream")
-                        .body(&body)
+                        .header("Content-Type", content_type)
+                        .body(decompressed)
                         .send()
-                        .await;
+                        .await?;
                 }
                 Err(err) => {
-                    return Response::new(&mut stream, &req)
+                    Response::new(stream, req)
                         .status(500)
-                        .body(format!("failed to read file: {err}").as_str())
+                        .body(format!("failed to read file: {err}").into_bytes())
                         .send()
-                        .await;
+                        .await?;
                 }
             }
-        } else if req.method == Method::Post {
+        }
+        RouteId::FilesPost => {
+            if config.static_files.is_none() {
+                return Response::new(stream, req).status(404).send().await;
+            }
+            let entity = req.param("name").expect("route guarantees {name}");
+            let fpath = format!(
+                "{}/{entity}",
+                config.static_files.as_ref().unwrap().to_str().unwrap()
+            );
             tokio::fs::write(fpath, &req.body).await?;
-            return Response::new(&mut stream, &req).status(201).send().await;
-        } else {
-            return Response::new(&mut stream, &req).status(400).send().await;
+            return Response::new(stream, req).status(201).send().await;
         }
-    } else {
-        Response::new(&mut stream, &req).status(404).send().await?;
     }
 
     Ok(())
@@ -239,6 +691,8 @@ struct Config {
     address: String,
     port: u16,
     static_files: Option<PathBuf>,
+    tls_cert: Option<PathBuf>,
+    tls_key: Option<PathBuf>,
 }
 
 #[tokio::main]
@@ -247,23 +701,54 @@ async fn main() -> anyhow::Result<()> {
         address: "127.0.0.1".into(),
         port: 4221,
         static_files: None,
+        tls_cert: None,
+        tls_key: None,
     };
     let listener = TcpListener::bind(format!("{}:{}", config.address, config.port)).await?;
     let mut it = env::args_os();
     while let Some(arg) = it.next() {
         if arg == "--directory" {
             config.static_files = it.next().map(|x| x.into());
+        } else if arg == "--tls-cert" {
+            config.tls_cert = it.next().map(|x| x.into());
+        } else if arg == "--tls-key" {
+            config.tls_key = it.next().map(|x| x.into());
         }
     }
 
     println!("Running with following config: {:?}", &config);
 
+    let router = build_router();
+
+    let tls_acceptor = match (&config.tls_cert, &config.tls_key) {
+        (Some(cert), Some(key)) => {
+            let tls_config = load_tls_config(cert, key)?;
+            Some(TlsAcceptor::from(Arc::new(tls_config)))
+        }
+        _ => None,
+    };
+
     loop {
         let conn = listener.accept().await;
         match conn {
             Ok((stream, _)) => {
                 let cfg = config.clone();
-                tokio::spawn(async move { handle_connection(stream, cfg).await });
+                let router = router.clone();
+                match tls_acceptor.clone() {
+                    Some(acceptor) => {
+                        tokio::spawn(async move {
+                            match acceptor.accept(stream).await {
+                                Ok(tls_stream) => {
+                                    let _ = handle_connection(tls_stream, cfg, router).await;
+                                }
+                                Err(e) => eprintln!("tls handshake failed: {e}"),
+                            }
+                        });
+                    }
+                    None => {
+                        tokio::spawn(async move { handle_connection(stream, cfg, router).await });
+                    }
+                }
             }
             Err(e) => {
                 eprintln!("error: {}", e);